@@ -1,53 +1,267 @@
 pub mod future {
+    use futures::future::FusedFuture;
     use pin_project::pin_project;
+    use std::any::Any;
     use std::future::Future;
+    use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
     use std::pin::Pin;
     use std::task::{Context, Poll};
 
+    /// Invokes the finalizer registered on a [`ThenFinally`], handing it the resolved output of
+    /// the future, or `None` if polling it panicked.
+    ///
+    /// Implemented both for plain `FnOnce(Option<&O>) -> Fut` closures and, via
+    /// [`IgnoreOutput`], for the value-less closures `then_finally` accepts.
+    #[doc(hidden)]
+    pub trait ThenFinalizer<O, Fut> {
+        fn finalize(self, output: Option<&O>) -> Fut;
+    }
+
+    /// Adapts a value-less `FnOnce() -> Fut` finalizer into a [`ThenFinalizer`] so
+    /// `then_finally` can share its implementation with `then_finally_with`.
+    #[doc(hidden)]
+    pub struct IgnoreOutput<F>(F);
+
+    impl<Fut, F: FnOnce() -> Fut, O> ThenFinalizer<O, Fut> for IgnoreOutput<F> {
+        fn finalize(self, _output: Option<&O>) -> Fut {
+            (self.0)()
+        }
+    }
+
+    impl<Fut, F: FnOnce(Option<&O>) -> Fut, O> ThenFinalizer<O, Fut> for F {
+        fn finalize(self, output: Option<&O>) -> Fut {
+            self(output)
+        }
+    }
+
+    /// Tracks where a [`ThenFinally`] is in its lifecycle, so its drop guard knows whether it's
+    /// firing on a cancellation or has already completed normally.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum DropState {
+        /// Still polling the wrapped future.
+        Running,
+        /// The wrapped future is done; the async finalizer is running.
+        Cleaning,
+        /// The async finalizer has run to completion.
+        Done,
+    }
+
+    /// Holds a synchronous cleanup guard that runs, at most once, when dropped while still
+    /// armed.
+    ///
+    /// `disarm` is called once a [`ThenFinally`]/[`Finally`] reaches [`DropState::Done`], so
+    /// this only ever fires on a pre-completion drop (task cancellation, a `select!` losing this
+    /// branch, a timeout) and never on the normal completion path. Keeping it as its own type
+    /// rather than a `Drop` impl on the combinator itself means only `G` — not the combinator's
+    /// other type parameters — is subject to dropck's stricter borrow rules for `Drop` types.
+    struct DropGuard<G: FnOnce()> {
+        guard: Option<G>,
+    }
+
+    impl<G: FnOnce()> DropGuard<G> {
+        fn disarm(&mut self) {
+            self.guard = None;
+        }
+    }
+
+    impl<G: FnOnce()> Drop for DropGuard<G> {
+        fn drop(&mut self) {
+            if let Some(guard) = self.guard.take() {
+                guard();
+            }
+        }
+    }
+
     #[pin_project]
-    pub struct ThenFinally<FT, Fut, F, O> {
+    pub struct ThenFinally<FT, Fut, F, O, G = fn()>
+    where
+        F: ThenFinalizer<O, Fut>,
+        G: FnOnce(),
+    {
         #[pin]
         item: Option<FT>,
         #[pin]
         fut: Option<Fut>,
         f: Option<F>,
         output: Option<O>,
+        panic: Option<Box<dyn Any + Send>>,
+        drop_guard: Option<DropGuard<G>>,
+        state: DropState,
     }
 
+    impl<FT, Fut, F, O> ThenFinally<FT, Fut, F, O, fn()>
+    where
+        F: ThenFinalizer<O, Fut>,
+    {
+        /// Attaches a synchronous cleanup guard that runs if this combinator is dropped (task
+        /// cancellation, a `select!` losing this branch, a timeout) before its async finalizer
+        /// has run to completion.
+        ///
+        /// Unlike the finalizer passed to [`then_finally`](ThenFinallyFutureExt::then_finally) /
+        /// [`then_finally_with`](ThenFinallyFutureExt::then_finally_with), `on_drop` can't await
+        /// anything — `Drop` is synchronous — so it's a best-effort fallback for cleanup that
+        /// must happen even when the async finalizer never gets to run, not a replacement for
+        /// it. It does not run if the future completes normally.
+        pub fn then_finally_on_drop<G: FnOnce()>(self, on_drop: G) -> ThenFinally<FT, Fut, F, O, G> {
+            ThenFinally {
+                item: self.item,
+                fut: self.fut,
+                f: self.f,
+                output: self.output,
+                panic: self.panic,
+                drop_guard: Some(DropGuard { guard: Some(on_drop) }),
+                state: self.state,
+            }
+        }
+    }
+
+    /// Resolves immediately; used as the no-op async finalizer for
+    /// [`ThenFinallyFutureExt::then_finally_on_drop`].
+    fn noop_cleanup() -> futures::future::Ready<()> {
+        futures::future::ready(())
+    }
+
+    /// The [`ThenFinally`] built by [`ThenFinallyFutureExt::then_finally_on_drop`]: a no-op async
+    /// finalizer plus the registered cancel guard.
+    type OnDropOnly<FT, O, G> = ThenFinally<
+        FT,
+        futures::future::Ready<()>,
+        IgnoreOutput<fn() -> futures::future::Ready<()>>,
+        O,
+        G,
+    >;
+
     pub trait ThenFinallyFutureExt: Sized {
         /// Consumes the current future into a new one which will execute an asynchronous upon completion of the future
         ///
         /// Note that this will execute the code regardless of a value that the future returns.
+        /// This also runs if polling the inner future panics, so the cleanup still fires before
+        /// the panic is propagated.
         fn then_finally<Fut: Future, F: FnOnce() -> Fut, O>(
             self,
             f: F,
+        ) -> ThenFinally<Self, Fut, IgnoreOutput<F>, O> {
+            ThenFinally {
+                item: Some(self),
+                fut: None,
+                f: Some(IgnoreOutput(f)),
+                output: None,
+                panic: None,
+                drop_guard: None,
+                state: DropState::Running,
+            }
+        }
+
+        /// Like [`then_finally`](Self::then_finally), but hands the finalizer the resolved output
+        /// of the future so cleanup can branch on it (e.g. commit vs. rollback).
+        ///
+        /// The `Option` is deliberate, not a widened stand-in for `&O`: `output` is `None` if
+        /// polling the inner future panicked, since the finalizer still runs before the panic is
+        /// resumed and there is genuinely no value to hand it in that case. A plain `&O` would
+        /// make that case unrepresentable.
+        fn then_finally_with<Fut: Future, F: FnOnce(Option<&O>) -> Fut, O>(
+            self,
+            f: F,
         ) -> ThenFinally<Self, Fut, F, O> {
             ThenFinally {
                 item: Some(self),
                 fut: None,
                 f: Some(f),
                 output: None,
+                panic: None,
+                drop_guard: None,
+                state: DropState::Running,
+            }
+        }
+
+        /// Like [`then_finally`](Self::then_finally), but the finalizer can itself fail: if the
+        /// returned future resolves to `Err`, that error becomes the terminal result instead of
+        /// the future's own output.
+        fn try_then_finally<E, Fut: Future<Output = Result<(), E>>, F: FnOnce() -> Fut, O>(
+            self,
+            f: F,
+        ) -> TryThenFinally<Self, Fut, IgnoreOutput<F>, O, E> {
+            TryThenFinally {
+                item: Some(self),
+                fut: None,
+                f: Some(IgnoreOutput(f)),
+                output: None,
+                panic: None,
             }
         }
+
+        /// Like [`try_then_finally`](Self::try_then_finally), but hands the finalizer the
+        /// resolved output of the future so cleanup can branch on it.
+        ///
+        /// As with [`then_finally_with`](Self::then_finally_with), the `Option` is intentional:
+        /// it is `None` only when polling the inner future panicked, not invoked with a value in
+        /// that case.
+        fn try_then_finally_with<
+            E,
+            Fut: Future<Output = Result<(), E>>,
+            F: FnOnce(Option<&O>) -> Fut,
+            O,
+        >(
+            self,
+            f: F,
+        ) -> TryThenFinally<Self, Fut, F, O, E> {
+            TryThenFinally {
+                item: Some(self),
+                fut: None,
+                f: Some(f),
+                output: None,
+                panic: None,
+            }
+        }
+
+        /// Shorthand for
+        /// `self.then_finally(|| futures::future::ready(())).then_finally_on_drop(on_drop)`: wraps this
+        /// future with only a synchronous cancel guard and no async finalizer of its own.
+        ///
+        /// See [`ThenFinally::then_finally_on_drop`] for the guard's semantics; chain
+        /// [`then_finally_with`](Self::then_finally_with) first if you also need async cleanup
+        /// on the normal completion path.
+        fn then_finally_on_drop<G: FnOnce()>(
+            self,
+            on_drop: G,
+        ) -> OnDropOnly<Self, Self::Output, G>
+        where
+            Self: Future,
+        {
+            self.then_finally::<_, _, Self::Output>(
+                noop_cleanup as fn() -> futures::future::Ready<()>,
+            )
+            .then_finally_on_drop(on_drop)
+        }
     }
 
     impl<T: Sized> ThenFinallyFutureExt for T {}
 
-    impl<FT: Future<Output = O>, Fut: Future, F, O> Future for ThenFinally<FT, Fut, F, O>
+    impl<FT: Future<Output = O>, Fut: Future, F, O, G> Future for ThenFinally<FT, Fut, F, O, G>
     where
-        F: FnOnce() -> Fut,
+        F: ThenFinalizer<O, Fut>,
+        G: FnOnce(),
     {
         type Output = FT::Output;
         fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
             let mut this = self.project();
 
             if let Some(item) = this.item.as_mut().as_pin_mut() {
-                let output = futures::ready!(item.poll(cx));
-                this.output.replace(output);
-                let func = this.f.take().expect("function is valid");
-                let fut = Some(func());
-                this.fut.set(fut);
+                match catch_unwind(AssertUnwindSafe(|| item.poll(cx))) {
+                    Ok(Poll::Pending) => return Poll::Pending,
+                    Ok(Poll::Ready(output)) => {
+                        let func = this.f.take().expect("function is valid");
+                        this.fut.set(Some(func.finalize(Some(&output))));
+                        this.output.replace(output);
+                    }
+                    Err(payload) => {
+                        this.panic.replace(payload);
+                        let func = this.f.take().expect("function is valid");
+                        this.fut.set(Some(func.finalize(None)));
+                    }
+                }
                 this.item.set(None);
+                *this.state = DropState::Cleaning;
             }
 
             if let Some(fut) = this.fut.as_mut().as_pin_mut() {
@@ -55,61 +269,369 @@ pub mod future {
                 this.fut.set(None);
             }
 
+            *this.state = DropState::Done;
+            if let Some(guard) = this.drop_guard.as_mut() {
+                guard.disarm();
+            }
+
+            if let Some(payload) = this.panic.take() {
+                resume_unwind(payload);
+            }
+
             let output = this.output.take();
 
             Poll::Ready(output.expect("output from future to be value"))
         }
     }
+
+    impl<FT: Future<Output = O>, Fut: Future, F, O, G> FusedFuture for ThenFinally<FT, Fut, F, O, G>
+    where
+        F: ThenFinalizer<O, Fut>,
+        G: FnOnce(),
+    {
+        fn is_terminated(&self) -> bool {
+            self.state == DropState::Done
+        }
+    }
+
+    #[pin_project]
+    pub struct TryThenFinally<FT, Fut, F, O, E>
+    where
+        F: ThenFinalizer<O, Fut>,
+        Fut: Future<Output = Result<(), E>>,
+    {
+        #[pin]
+        item: Option<FT>,
+        #[pin]
+        fut: Option<Fut>,
+        f: Option<F>,
+        output: Option<O>,
+        panic: Option<Box<dyn Any + Send>>,
+    }
+
+    impl<FT: Future<Output = O>, Fut: Future<Output = Result<(), E>>, F, O, E> Future
+        for TryThenFinally<FT, Fut, F, O, E>
+    where
+        F: ThenFinalizer<O, Fut>,
+    {
+        type Output = Result<O, E>;
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let mut this = self.project();
+
+            if let Some(item) = this.item.as_mut().as_pin_mut() {
+                match catch_unwind(AssertUnwindSafe(|| item.poll(cx))) {
+                    Ok(Poll::Pending) => return Poll::Pending,
+                    Ok(Poll::Ready(output)) => {
+                        let func = this.f.take().expect("function is valid");
+                        this.fut.set(Some(func.finalize(Some(&output))));
+                        this.output.replace(output);
+                    }
+                    Err(payload) => {
+                        this.panic.replace(payload);
+                        let func = this.f.take().expect("function is valid");
+                        this.fut.set(Some(func.finalize(None)));
+                    }
+                }
+                this.item.set(None);
+            }
+
+            let mut cleanup_result = Ok(());
+            if let Some(fut) = this.fut.as_mut().as_pin_mut() {
+                cleanup_result = futures::ready!(fut.poll(cx));
+                this.fut.set(None);
+            }
+
+            if let Some(payload) = this.panic.take() {
+                resume_unwind(payload);
+            }
+
+            match cleanup_result {
+                Ok(()) => {
+                    let output = this.output.take();
+                    Poll::Ready(Ok(output.expect("output from future to be value")))
+                }
+                Err(e) => {
+                    this.output.take();
+                    Poll::Ready(Err(e))
+                }
+            }
+        }
+    }
+
+    impl<FT: Future<Output = O>, Fut: Future<Output = Result<(), E>>, F, O, E> FusedFuture
+        for TryThenFinally<FT, Fut, F, O, E>
+    where
+        F: ThenFinalizer<O, Fut>,
+    {
+        fn is_terminated(&self) -> bool {
+            self.item.is_none() && self.fut.is_none() && self.output.is_none()
+        }
+    }
 }
 
 pub mod stream {
+    use futures::stream::FusedStream;
     use futures::{Future, Stream};
     use pin_project::pin_project;
+    use std::any::Any;
+    use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
     use std::pin::Pin;
     use std::task::{Context, Poll};
 
+    /// Summary of how a stream wrapped in [`finally`](FinallyStreamExt::finally) ended, handed to
+    /// the finalizer closure registered via [`finally_with`](FinallyStreamExt::finally_with).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct FinallySummary {
+        /// The number of items the stream yielded before ending.
+        pub items: usize,
+        /// `true` if the stream ran to natural exhaustion, `false` if it ended because polling
+        /// it panicked.
+        pub exhausted: bool,
+    }
+
+    /// Invokes the finalizer registered on a [`Finally`], handing it a [`FinallySummary`] of how
+    /// the stream ended.
+    ///
+    /// Implemented both for plain `FnOnce(FinallySummary) -> Fut` closures and, via
+    /// [`IgnoreSummary`], for the value-less closures `finally` accepts.
+    #[doc(hidden)]
+    pub trait StreamFinalizer<Fut> {
+        fn finalize(self, summary: FinallySummary) -> Fut;
+    }
+
+    /// Adapts a value-less `FnOnce() -> Fut` finalizer into a [`StreamFinalizer`] so `finally`
+    /// can share its implementation with `finally_with`.
+    #[doc(hidden)]
+    pub struct IgnoreSummary<F>(F);
+
+    impl<Fut, F: FnOnce() -> Fut> StreamFinalizer<Fut> for IgnoreSummary<F> {
+        fn finalize(self, _summary: FinallySummary) -> Fut {
+            (self.0)()
+        }
+    }
+
+    impl<Fut, F: FnOnce(FinallySummary) -> Fut> StreamFinalizer<Fut> for F {
+        fn finalize(self, summary: FinallySummary) -> Fut {
+            self(summary)
+        }
+    }
+
+    /// Tracks where a [`Finally`] is in its lifecycle, so its drop guard knows whether it's
+    /// firing on a cancellation or has already completed normally.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum DropState {
+        /// Still polling the wrapped stream.
+        Running,
+        /// The wrapped stream is exhausted; the async finalizer is running.
+        Cleaning,
+        /// The async finalizer has run to completion.
+        Done,
+    }
+
+    /// Holds a synchronous cleanup guard that runs, at most once, when dropped while still
+    /// armed.
+    ///
+    /// `disarm` is called once a [`Finally`] reaches [`DropState::Done`], so this only ever
+    /// fires on a pre-completion drop (task cancellation, a `select!` losing this branch, a
+    /// timeout) and never on the normal completion path. Keeping it as its own type rather than
+    /// a `Drop` impl on the combinator itself means only `G` — not the combinator's other type
+    /// parameters — is subject to dropck's stricter borrow rules for `Drop` types.
+    struct DropGuard<G: FnOnce()> {
+        guard: Option<G>,
+    }
+
+    impl<G: FnOnce()> DropGuard<G> {
+        fn disarm(&mut self) {
+            self.guard = None;
+        }
+    }
+
+    impl<G: FnOnce()> Drop for DropGuard<G> {
+        fn drop(&mut self) {
+            if let Some(guard) = self.guard.take() {
+                guard();
+            }
+        }
+    }
+
     #[pin_project]
-    pub struct Finally<ST, Fut, F> {
+    pub struct Finally<ST, Fut, F, G = fn()>
+    where
+        F: StreamFinalizer<Fut>,
+        G: FnOnce(),
+    {
         #[pin]
         item: Option<ST>,
         #[pin]
         fut: Option<Fut>,
         f: Option<F>,
+        panic: Option<Box<dyn Any + Send>>,
+        count: usize,
+        drop_guard: Option<DropGuard<G>>,
+        state: DropState,
     }
 
+    impl<ST, Fut, F> Finally<ST, Fut, F, fn()>
+    where
+        F: StreamFinalizer<Fut>,
+    {
+        /// Attaches a synchronous cleanup guard that runs if this combinator is dropped (task
+        /// cancellation, a `select!` losing this branch, a timeout) before its async finalizer
+        /// has run to completion.
+        ///
+        /// Unlike the finalizer passed to [`finally`](FinallyStreamExt::finally) /
+        /// [`finally_with`](FinallyStreamExt::finally_with), `on_drop` can't await anything —
+        /// `Drop` is synchronous — so it's a best-effort fallback for cleanup that must happen
+        /// even when the async finalizer never gets to run, not a replacement for it. It does
+        /// not run if the stream is exhausted normally.
+        pub fn finally_on_drop<G: FnOnce()>(self, on_drop: G) -> Finally<ST, Fut, F, G> {
+            Finally {
+                item: self.item,
+                fut: self.fut,
+                f: self.f,
+                panic: self.panic,
+                count: self.count,
+                drop_guard: Some(DropGuard { guard: Some(on_drop) }),
+                state: self.state,
+            }
+        }
+    }
+
+    /// Resolves immediately; used as the no-op async finalizer for
+    /// [`FinallyStreamExt::finally_on_drop`].
+    fn noop_cleanup() -> futures::future::Ready<()> {
+        futures::future::ready(())
+    }
+
+    /// The [`Finally`] built by [`FinallyStreamExt::finally_on_drop`]: a no-op async finalizer
+    /// plus the registered cancel guard.
+    type OnDropOnly<ST, G> = Finally<
+        ST,
+        futures::future::Ready<()>,
+        IgnoreSummary<fn() -> futures::future::Ready<()>>,
+        G,
+    >;
+
     pub trait FinallyStreamExt: Sized {
         /// Consumes the current stream into a new one which will execute an asynchronous upon completion of the stream
         ///
         /// Note that this will execute the code regardless of a value that the stream returns.
-        fn finally<Fut: Future, F: FnOnce() -> Fut>(self, f: F) -> Finally<Self, Fut, F> {
+        /// This also runs if polling the inner stream panics, so the cleanup still fires before
+        /// the panic is propagated.
+        fn finally<Fut: Future, F: FnOnce() -> Fut>(self, f: F) -> Finally<Self, Fut, IgnoreSummary<F>> {
             Finally {
+                item: Some(self),
+                fut: None,
+                f: Some(IgnoreSummary(f)),
+                panic: None,
+                count: 0,
+                drop_guard: None,
+                state: DropState::Running,
+            }
+        }
+
+        /// Like [`finally`](Self::finally), but hands the finalizer a [`FinallySummary`]
+        /// describing how the stream ended.
+        fn finally_with<Fut: Future, F: FnOnce(FinallySummary) -> Fut>(
+            self,
+            f: F,
+        ) -> Finally<Self, Fut, F> {
+            Finally {
+                item: Some(self),
+                fut: None,
+                f: Some(f),
+                panic: None,
+                count: 0,
+                drop_guard: None,
+                state: DropState::Running,
+            }
+        }
+
+        /// Like [`finally`](Self::finally), but the finalizer can itself fail: if the returned
+        /// future resolves to `Err`, that error is yielded as a trailing `Some(Err(e))` item
+        /// after the stream's last item.
+        fn finally_or_fail<E, Fut: Future<Output = Result<(), E>>, F: FnOnce() -> Fut>(
+            self,
+            f: F,
+        ) -> FallibleFinally<Self, Fut, IgnoreSummary<F>, E> {
+            FallibleFinally {
+                item: Some(self),
+                fut: None,
+                f: Some(IgnoreSummary(f)),
+                panic: None,
+                count: 0,
+                pending_error: None,
+            }
+        }
+
+        /// Like [`finally_or_fail`](Self::finally_or_fail), but hands the finalizer a [`FinallySummary`]
+        /// describing how the stream ended.
+        fn finally_or_fail_with<
+            E,
+            Fut: Future<Output = Result<(), E>>,
+            F: FnOnce(FinallySummary) -> Fut,
+        >(
+            self,
+            f: F,
+        ) -> FallibleFinally<Self, Fut, F, E> {
+            FallibleFinally {
                 item: Some(self),
                 fut: None,
                 f: Some(f),
+                panic: None,
+                count: 0,
+                pending_error: None,
             }
         }
+
+        /// Shorthand for `self.finally(|| futures::future::ready(())).finally_on_drop(on_drop)`:
+        /// wraps this stream with
+        /// only a synchronous cancel guard and no async finalizer of its own.
+        ///
+        /// See [`Finally::finally_on_drop`] for the guard's semantics; chain
+        /// [`finally_with`](Self::finally_with) first if you also need async cleanup on the
+        /// normal completion path.
+        fn finally_on_drop<G: FnOnce()>(self, on_drop: G) -> OnDropOnly<Self, G>
+        where
+            Self: Stream,
+        {
+            self.finally(noop_cleanup as fn() -> futures::future::Ready<()>)
+                .finally_on_drop(on_drop)
+        }
     }
 
     impl<T: Sized> FinallyStreamExt for T {}
 
-    impl<ST: Stream, Fut: Future, F> Stream for Finally<ST, Fut, F>
+    impl<ST: Stream, Fut: Future, F, G> Stream for Finally<ST, Fut, F, G>
     where
-        F: FnOnce() -> Fut,
+        F: StreamFinalizer<Fut>,
+        G: FnOnce(),
     {
         type Item = ST::Item;
         fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
             let mut this = self.project();
 
             if let Some(item) = this.item.as_mut().as_pin_mut() {
-                match futures::ready!(item.poll_next(cx)) {
-                    Some(item) => return Poll::Ready(Some(item)),
-                    None => {
-                        let func = this.f.take().expect("function is valid");
-                        let fut = Some(func());
-                        this.fut.set(fut);
-                        this.item.set(None);
+                let exhausted = match catch_unwind(AssertUnwindSafe(|| item.poll_next(cx))) {
+                    Ok(Poll::Pending) => return Poll::Pending,
+                    Ok(Poll::Ready(Some(item))) => {
+                        *this.count += 1;
+                        return Poll::Ready(Some(item));
+                    }
+                    Ok(Poll::Ready(None)) => true,
+                    Err(payload) => {
+                        this.panic.replace(payload);
+                        false
                     }
                 };
+                let summary = FinallySummary {
+                    items: *this.count,
+                    exhausted,
+                };
+                let func = this.f.take().expect("function is valid");
+                this.fut.set(Some(func.finalize(summary)));
+                this.item.set(None);
+                *this.state = DropState::Cleaning;
             }
 
             if let Some(fut) = this.fut.as_mut().as_pin_mut() {
@@ -117,31 +639,181 @@ pub mod stream {
                 this.fut.set(None);
             }
 
+            *this.state = DropState::Done;
+            if let Some(guard) = this.drop_guard.as_mut() {
+                guard.disarm();
+            }
+
+            if let Some(payload) = this.panic.take() {
+                resume_unwind(payload);
+            }
+
             Poll::Ready(None)
         }
     }
+
+    impl<ST: Stream, Fut: Future, F, G> FusedStream for Finally<ST, Fut, F, G>
+    where
+        F: StreamFinalizer<Fut>,
+        G: FnOnce(),
+    {
+        fn is_terminated(&self) -> bool {
+            self.state == DropState::Done
+        }
+    }
+
+    #[pin_project]
+    pub struct FallibleFinally<ST, Fut, F, E>
+    where
+        F: StreamFinalizer<Fut>,
+        Fut: Future<Output = Result<(), E>>,
+    {
+        #[pin]
+        item: Option<ST>,
+        #[pin]
+        fut: Option<Fut>,
+        f: Option<F>,
+        panic: Option<Box<dyn Any + Send>>,
+        count: usize,
+        pending_error: Option<E>,
+    }
+
+    impl<ST: Stream, Fut: Future<Output = Result<(), E>>, F, E> Stream
+        for FallibleFinally<ST, Fut, F, E>
+    where
+        F: StreamFinalizer<Fut>,
+    {
+        type Item = Result<ST::Item, E>;
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let mut this = self.project();
+
+            if let Some(item) = this.item.as_mut().as_pin_mut() {
+                let exhausted = match catch_unwind(AssertUnwindSafe(|| item.poll_next(cx))) {
+                    Ok(Poll::Pending) => return Poll::Pending,
+                    Ok(Poll::Ready(Some(item))) => {
+                        *this.count += 1;
+                        return Poll::Ready(Some(Ok(item)));
+                    }
+                    Ok(Poll::Ready(None)) => true,
+                    Err(payload) => {
+                        this.panic.replace(payload);
+                        false
+                    }
+                };
+                let summary = FinallySummary {
+                    items: *this.count,
+                    exhausted,
+                };
+                let func = this.f.take().expect("function is valid");
+                this.fut.set(Some(func.finalize(summary)));
+                this.item.set(None);
+            }
+
+            if let Some(fut) = this.fut.as_mut().as_pin_mut() {
+                let result = futures::ready!(fut.poll(cx));
+                this.fut.set(None);
+                if let Err(e) = result {
+                    this.pending_error.replace(e);
+                }
+            }
+
+            if let Some(payload) = this.panic.take() {
+                resume_unwind(payload);
+            }
+
+            if let Some(e) = this.pending_error.take() {
+                return Poll::Ready(Some(Err(e)));
+            }
+
+            Poll::Ready(None)
+        }
+    }
+
+    impl<ST: Stream, Fut: Future<Output = Result<(), E>>, F, E> FusedStream
+        for FallibleFinally<ST, Fut, F, E>
+    where
+        F: StreamFinalizer<Fut>,
+    {
+        fn is_terminated(&self) -> bool {
+            self.item.is_none() && self.fut.is_none() && self.pending_error.is_none()
+        }
+    }
 }
 
 pub mod try_stream {
+    use futures::stream::FusedStream;
     use futures::{Future, Stream, TryStream};
     use pin_project::pin_project;
+    use std::any::Any;
+    use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
     use std::pin::Pin;
     use std::task::{Context, Poll};
 
+    /// Invokes the finalizer registered on a [`TryFinally`], handing it the error the stream
+    /// ended with, or `None` if it ran to completion without one.
+    ///
+    /// Implemented both for plain `FnOnce(Option<&E>) -> Fut` closures and, via
+    /// [`IgnoreError`], for the value-less closures `try_finally` accepts.
+    #[doc(hidden)]
+    pub trait TryStreamFinalizer<E, Fut> {
+        fn finalize(self, error: Option<&E>) -> Fut;
+    }
+
+    /// Adapts a value-less `FnOnce() -> Fut` finalizer into a [`TryStreamFinalizer`] so
+    /// `try_finally` can share its implementation with `try_finally_with`.
+    #[doc(hidden)]
+    pub struct IgnoreError<F>(F);
+
+    impl<Fut, F: FnOnce() -> Fut, E> TryStreamFinalizer<E, Fut> for IgnoreError<F> {
+        fn finalize(self, _error: Option<&E>) -> Fut {
+            (self.0)()
+        }
+    }
+
+    impl<Fut, F: FnOnce(Option<&E>) -> Fut, E> TryStreamFinalizer<E, Fut> for F {
+        fn finalize(self, error: Option<&E>) -> Fut {
+            self(error)
+        }
+    }
+
     #[pin_project]
     pub struct TryFinally<ST, Fut, F>
     where
         ST: TryStream,
+        F: TryStreamFinalizer<ST::Error, Fut>,
     {
         #[pin]
         item: Option<ST>,
         #[pin]
         fut: Option<Fut>,
         f: Option<F>,
+        panic: Option<Box<dyn Any + Send>>,
+        pending_error: Option<ST::Error>,
     }
 
     pub trait FinallyTryStreamExt: Sized {
-        fn try_finally<Fut: Future, F: FnOnce() -> Fut>(self, f: F) -> TryFinally<Self, Fut, F>
+        fn try_finally<Fut: Future, F: FnOnce() -> Fut>(
+            self,
+            f: F,
+        ) -> TryFinally<Self, Fut, IgnoreError<F>>
+        where
+            Self: TryStream,
+        {
+            TryFinally {
+                item: Some(self),
+                fut: None,
+                f: Some(IgnoreError(f)),
+                panic: None,
+                pending_error: None,
+            }
+        }
+
+        /// Like [`try_finally`](Self::try_finally), but hands the finalizer the error the stream
+        /// ended with, or `None` if it ran to completion without one.
+        fn try_finally_with<Fut: Future, F: FnOnce(Option<&Self::Error>) -> Fut>(
+            self,
+            f: F,
+        ) -> TryFinally<Self, Fut, F>
         where
             Self: TryStream,
         {
@@ -149,6 +821,8 @@ pub mod try_stream {
                 item: Some(self),
                 fut: None,
                 f: Some(f),
+                panic: None,
+                pending_error: None,
             }
         }
     }
@@ -157,25 +831,34 @@ pub mod try_stream {
 
     impl<ST: TryStream, Fut: Future, F> Stream for TryFinally<ST, Fut, F>
     where
-        F: FnOnce() -> Fut,
+        F: TryStreamFinalizer<ST::Error, Fut>,
     {
         type Item = Result<ST::Ok, ST::Error>;
         fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
             let mut this = self.project();
 
             if let Some(item) = this.item.as_mut().as_pin_mut() {
-                let result = futures::ready!(item.try_poll_next(cx));
+                let result = match catch_unwind(AssertUnwindSafe(|| item.try_poll_next(cx))) {
+                    Ok(Poll::Pending) => return Poll::Pending,
+                    Ok(Poll::Ready(result)) => result,
+                    Err(payload) => {
+                        this.panic.replace(payload);
+                        None
+                    }
+                };
 
                 match result {
                     Some(Ok(val)) => return Poll::Ready(Some(Ok(val))),
                     Some(Err(e)) => {
+                        let func = this.f.take().expect("function is valid");
+                        let fut = Some(func.finalize(Some(&e)));
+                        this.fut.set(fut);
                         this.item.set(None);
-                        this.f.take();
-                        return Poll::Ready(Some(Err(e)));
+                        this.pending_error.replace(e);
                     }
                     None => {
                         let func = this.f.take().expect("function is valid");
-                        let fut = Some(func());
+                        let fut = Some(func.finalize(None));
                         this.fut.set(fut);
                         this.item.set(None);
                     }
@@ -187,18 +870,37 @@ pub mod try_stream {
                 this.fut.set(None);
             }
 
+            if let Some(payload) = this.panic.take() {
+                resume_unwind(payload);
+            }
+
+            if let Some(e) = this.pending_error.take() {
+                return Poll::Ready(Some(Err(e)));
+            }
+
             Poll::Ready(None)
         }
     }
+
+    impl<ST: TryStream, Fut: Future, F> FusedStream for TryFinally<ST, Fut, F>
+    where
+        F: TryStreamFinalizer<ST::Error, Fut>,
+    {
+        fn is_terminated(&self) -> bool {
+            self.item.is_none() && self.fut.is_none() && self.pending_error.is_none()
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::future::ThenFinallyFutureExt;
-    use crate::stream::FinallyStreamExt;
+    use crate::stream::{FinallyStreamExt, FinallySummary};
+    use crate::try_stream::FinallyTryStreamExt;
+    use futures::future::FusedFuture;
+    use futures::stream::FusedStream;
     use futures::{StreamExt, TryStreamExt};
     use std::convert::Infallible;
-    use crate::try_stream::FinallyTryStreamExt;
 
     #[test]
     fn future_final() {
@@ -253,16 +955,268 @@ mod test {
                 Err::<i8, std::io::Error>(std::io::ErrorKind::Other.into())
             })
             .try_finally(|| async {
-                unreachable!()
+                val = 2;
             });
 
             futures::pin_mut!(st);
 
-            while let Ok(_) = st.try_next().await {
-                unreachable!()
-            }
+            assert!(st.try_next().await.is_err());
+            assert_eq!(val, 2);
+        });
+    }
 
-            assert_eq!(val, 1);
+    #[test]
+    fn future_panic_runs_finalizer() {
+        let ran = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let ran2 = ran.clone();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            futures::executor::block_on(async move {
+                futures::future::lazy(|_| panic!("boom"))
+                    .then_finally(|| async move {
+                        ran2.store(true, std::sync::atomic::Ordering::SeqCst);
+                    })
+                    .await;
+            })
+        }));
+
+        assert!(result.is_err());
+        assert!(ran.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[test]
+    fn stream_panic_runs_finalizer() {
+        let ran = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let ran2 = ran.clone();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            futures::executor::block_on(async move {
+                let st = futures::stream::poll_fn(|_| {
+                    panic!("boom");
+                    #[allow(unreachable_code)]
+                    std::task::Poll::Ready(None::<()>)
+                })
+                .finally(|| async move {
+                    ran2.store(true, std::sync::atomic::Ordering::SeqCst);
+                });
+
+                futures::pin_mut!(st);
+                st.next().await;
+            })
+        }));
+
+        assert!(result.is_err());
+        assert!(ran.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[test]
+    fn future_is_terminated() {
+        futures::executor::block_on(async move {
+            let fut = futures::future::ready(()).then_finally(|| async {});
+            futures::pin_mut!(fut);
+
+            assert!(!fut.is_terminated());
+            (&mut fut).await;
+            assert!(fut.is_terminated());
+        });
+    }
+
+    #[test]
+    fn stream_is_terminated() {
+        futures::executor::block_on(async move {
+            let st = futures::stream::once(async { 0 }).finally(|| async {});
+            futures::pin_mut!(st);
+
+            assert!(!st.is_terminated());
+            while st.next().await.is_some() {}
+            assert!(st.is_terminated());
+        });
+    }
+
+    #[test]
+    fn then_finally_with_sees_output() {
+        futures::executor::block_on(async move {
+            let mut seen = None;
+
+            futures::future::ready(42)
+                .then_finally_with(|output: Option<&i32>| {
+                    seen = output.copied();
+                    async {}
+                })
+                .await;
+
+            assert_eq!(seen, Some(42));
+        });
+    }
+
+    #[test]
+    fn finally_with_sees_summary() {
+        futures::executor::block_on(async move {
+            let mut summary = None;
+
+            let st = futures::stream::iter([1, 2, 3]).finally_with(|s| {
+                summary = Some(s);
+                async {}
+            });
+            futures::pin_mut!(st);
+
+            while st.next().await.is_some() {}
+
+            assert_eq!(
+                summary,
+                Some(FinallySummary {
+                    items: 3,
+                    exhausted: true,
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn try_finally_with_sees_no_error_on_success() {
+        futures::executor::block_on(async move {
+            let mut saw_error = false;
+
+            let st = futures::stream::once(async { Ok::<_, Infallible>(0) })
+                .try_finally_with(|e: Option<&Infallible>| {
+                    saw_error = e.is_some();
+                    async {}
+                });
+            futures::pin_mut!(st);
+
+            while let Ok(Some(_)) = st.try_next().await {}
+
+            assert!(!saw_error);
+        });
+    }
+
+    #[test]
+    fn try_then_finally_aborts_on_cleanup_failure() {
+        futures::executor::block_on(async move {
+            let result = futures::future::ready(42)
+                .try_then_finally(|| async { Err::<(), &str>("cleanup failed") })
+                .await;
+
+            assert_eq!(result, Err("cleanup failed"));
+        });
+    }
+
+    #[test]
+    fn finally_or_fail_aborts_stream_on_cleanup_failure() {
+        futures::executor::block_on(async move {
+            let st = futures::stream::iter([1, 2, 3])
+                .finally_or_fail(|| async { Err::<(), &str>("cleanup failed") });
+            futures::pin_mut!(st);
+
+            let items: Vec<_> = st.collect().await;
+
+            assert_eq!(
+                items,
+                vec![Ok(1), Ok(2), Ok(3), Err("cleanup failed")]
+            );
+        });
+    }
+
+    #[test]
+    fn then_finally_on_drop_runs_guard_when_cancelled() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let ran = Arc::new(AtomicBool::new(false));
+        let ran_in_guard = ran.clone();
+
+        let fut = futures::future::pending::<()>().then_finally_on_drop(move || {
+            ran_in_guard.store(true, Ordering::SeqCst);
+        });
+        drop(fut);
+
+        assert!(ran.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn then_finally_on_drop_skips_guard_after_normal_completion() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        futures::executor::block_on(async move {
+            let ran = Arc::new(AtomicBool::new(false));
+            let ran_in_guard = ran.clone();
+
+            futures::future::ready(())
+                .then_finally_on_drop(move || {
+                    ran_in_guard.store(true, Ordering::SeqCst);
+                })
+                .await;
+
+            assert!(!ran.load(Ordering::SeqCst));
+        });
+    }
+
+    #[test]
+    fn then_finally_on_drop_composes_with_async_finalizer() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        futures::executor::block_on(async move {
+            let finalizer_ran = Arc::new(AtomicBool::new(false));
+            let finalizer_ran2 = finalizer_ran.clone();
+            let guard_ran = Arc::new(AtomicBool::new(false));
+            let guard_ran2 = guard_ran.clone();
+
+            futures::future::ready(())
+                .then_finally(move || async move {
+                    finalizer_ran2.store(true, Ordering::SeqCst);
+                })
+                .then_finally_on_drop(move || {
+                    guard_ran2.store(true, Ordering::SeqCst);
+                })
+                .await;
+
+            assert!(finalizer_ran.load(Ordering::SeqCst));
+            assert!(!guard_ran.load(Ordering::SeqCst));
+        });
+    }
+
+    #[test]
+    fn stream_finally_on_drop_runs_guard_when_cancelled() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let ran = Arc::new(AtomicBool::new(false));
+        let ran_in_guard = ran.clone();
+
+        let st = futures::stream::pending::<i32>().finally_on_drop(move || {
+            ran_in_guard.store(true, Ordering::SeqCst);
+        });
+        drop(st);
+
+        assert!(ran.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn stream_finally_on_drop_composes_with_async_finalizer() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        futures::executor::block_on(async move {
+            let finalizer_ran = Arc::new(AtomicBool::new(false));
+            let finalizer_ran2 = finalizer_ran.clone();
+            let guard_ran = Arc::new(AtomicBool::new(false));
+            let guard_ran2 = guard_ran.clone();
+
+            let st = futures::stream::iter([1, 2, 3])
+                .finally(move || async move {
+                    finalizer_ran2.store(true, Ordering::SeqCst);
+                })
+                .finally_on_drop(move || {
+                    guard_ran2.store(true, Ordering::SeqCst);
+                });
+            futures::pin_mut!(st);
+
+            while st.next().await.is_some() {}
+
+            assert!(finalizer_ran.load(Ordering::SeqCst));
+            assert!(!guard_ran.load(Ordering::SeqCst));
         });
     }
 }